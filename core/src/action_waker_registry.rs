@@ -0,0 +1,52 @@
+extern crate futures;
+
+use action::ActionWrapper;
+use futures::task::Waker;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Shared registry of wakers that are waiting on a particular `ActionWrapper`
+/// to be resolved in state. `Context` holds one of these behind an `Arc` so
+/// that action-creator futures (e.g. `RemoveEntryFuture`) and the instance's
+/// action-processing loop can both reach it without a direct dependency
+/// between them.
+#[derive(Clone, Default)]
+pub struct ActionWakerRegistry {
+    wakers: Arc<Mutex<HashMap<ActionWrapper, Vec<Waker>>>>,
+}
+
+impl ActionWakerRegistry {
+    pub fn new() -> Self {
+        ActionWakerRegistry {
+            wakers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Register `waker` to be woken the next time `action` resolves in state.
+    pub fn register(&self, action: ActionWrapper, waker: Waker) {
+        self.wakers
+            .lock()
+            .expect("action waker registry lock poisoned")
+            .entry(action)
+            .or_insert_with(Vec::new)
+            .push(waker);
+    }
+
+    /// Wake and drop every waker registered for `action`, if any.
+    /// Called from the instance's action-processing loop once a reducer
+    /// has written the action's response into state.
+    pub fn wake(&self, action: &ActionWrapper) {
+        if let Some(wakers) = self
+            .wakers
+            .lock()
+            .expect("action waker registry lock poisoned")
+            .remove(action)
+        {
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use action_waker_registry::ActionWakerRegistry;
+use state::State;
+use std::sync::{Arc, RwLock};
+
+/// Cross-cutting state reached from anywhere holding an `Arc<Context>`:
+/// the latest reduced `State` snapshot, and the registry action-creator
+/// futures use to be woken when the action they are tracking resolves in
+/// state, instead of busy-polling on every `poll`.
+pub struct Context {
+    state: RwLock<Option<Arc<State>>>,
+    action_waker_registry: ActionWakerRegistry,
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context {
+            state: RwLock::new(None),
+            action_waker_registry: ActionWakerRegistry::new(),
+        }
+    }
+
+    /// the most recent state produced by the instance's reducers, if any
+    /// has been committed yet
+    pub fn state(&self) -> Option<Arc<State>> {
+        self.state
+            .read()
+            .expect("context state lock poisoned")
+            .clone()
+    }
+
+    /// replace the current state snapshot; called by the instance's
+    /// action-processing loop once a reducer has produced a new one
+    pub fn set_state(&self, new_state: Arc<State>) {
+        *self.state.write().expect("context state lock poisoned") = Some(new_state);
+    }
+
+    /// the registry of wakers waiting on DHT/CAS actions to resolve in state
+    pub fn action_waker_registry(&self) -> &ActionWakerRegistry {
+        &self.action_waker_registry
+    }
+}
@@ -38,11 +38,23 @@ impl Future for RemoveEntryFuture {
         &mut self,
         cx: &mut futures::task::Context<'_>,
     ) -> Result<futures::Async<Self::Item>, Self::Error> {
-        //
-        // TODO: connect the waker to state updates for performance reasons
-        // See: https://github.com/holochain/holochain-rust/issues/314
-        //
-        cx.waker().wake();
+        if let Some(state) = self.context.state() {
+            match state.dht().actions().get(&self.action) {
+                Some(Ok(_)) => return Ok(futures::Async::Ready(())),
+                Some(Err(e)) => return Err(e.clone()),
+                None => (),
+            }
+        }
+
+        // Register before the second check below to close the race where the
+        // action resolves between our first lookup and this registration: the
+        // instance's action-processing loop only wakes wakers that are already
+        // registered when it drains a resolved action, so we must be in the
+        // registry before we re-check state, not after.
+        self.context
+            .action_waker_registry()
+            .register(self.action.clone(), cx.waker().clone());
+
         if let Some(state) = self.context.state() {
             match state.dht().actions().get(&self.action) {
                 Some(Ok(_)) => Ok(futures::Async::Ready(())),
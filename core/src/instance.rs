@@ -0,0 +1,19 @@
+use action::ActionWrapper;
+use context::Context;
+use std::sync::{mpsc::SyncSender, Arc};
+
+/// send `action_wrapper` to the instance's action-processing loop
+pub fn dispatch_action(action_channel: &SyncSender<ActionWrapper>, action_wrapper: ActionWrapper) {
+    action_channel
+        .send(action_wrapper)
+        .expect("action channel must be open");
+}
+
+/// called by the instance's action-processing loop once a reducer has
+/// written `action`'s response into the new state it just committed to
+/// `context`: drains and wakes every future that registered interest in
+/// `action` via `Context::action_waker_registry`, so `RemoveEntryFuture`
+/// (and its siblings) resolve on their next poll instead of spinning.
+pub fn notify_action_resolved(context: &Arc<Context>, action: &ActionWrapper) {
+    context.action_waker_registry().wake(action);
+}
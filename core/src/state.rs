@@ -0,0 +1,38 @@
+use action::ActionWrapper;
+use holochain_core_types::error::HolochainError;
+use std::collections::HashMap;
+
+/// minimal slice of the DHT's reduced state needed by DHT/CAS action
+/// futures: a map of every action that has been dispatched to its response,
+/// once a reducer has resolved it
+#[derive(Clone, Default)]
+pub struct DhtStore {
+    actions: HashMap<ActionWrapper, Result<(), HolochainError>>,
+}
+
+impl DhtStore {
+    pub fn actions(&self) -> &HashMap<ActionWrapper, Result<(), HolochainError>> {
+        &self.actions
+    }
+
+    pub fn actions_mut(&mut self) -> &mut HashMap<ActionWrapper, Result<(), HolochainError>> {
+        &mut self.actions
+    }
+}
+
+/// a single immutable snapshot of instance state, as produced by the
+/// reducers in response to dispatched actions
+#[derive(Clone, Default)]
+pub struct State {
+    dht: DhtStore,
+}
+
+impl State {
+    pub fn dht(&self) -> &DhtStore {
+        &self.dht
+    }
+
+    pub fn dht_mut(&mut self) -> &mut DhtStore {
+        &mut self.dht
+    }
+}
@@ -0,0 +1,53 @@
+use multihash::{encode, Hash};
+use rust_base58::ToBase58;
+use std::fmt::Debug;
+
+/// the raw bytes of some piece of content, before it is hashed into an
+/// `Address`
+pub type Content = String;
+
+/// a multihash-encoded, base58-formatted address derived from the content it
+/// identifies
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Address(String);
+
+impl Address {
+    pub fn new(s: &str) -> Address {
+        Address(s.to_string())
+    }
+}
+
+impl From<String> for Address {
+    fn from(s: String) -> Address {
+        Address(s)
+    }
+}
+
+impl From<&'static str> for Address {
+    fn from(s: &str) -> Address {
+        Address(s.to_string())
+    }
+}
+
+impl ToString for Address {
+    fn to_string(&self) -> String {
+        self.0.clone()
+    }
+}
+
+/// something that can be stored in a `ContentAddressableStorage`, keyed by
+/// the hash of its own content
+pub trait AddressableContent: Clone + Debug {
+    /// the address this content will be stored and retrieved under
+    fn address(&self) -> Address {
+        let hashed = encode(Hash::SHA2256, self.content().as_bytes())
+            .expect("failed to hash content for address");
+        Address(hashed.into_bytes().to_base58())
+    }
+
+    /// the raw content to be persisted
+    fn content(&self) -> Content;
+
+    /// reconstruct `Self` from previously-persisted content
+    fn from_content(content: &Content) -> Self;
+}
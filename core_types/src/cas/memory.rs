@@ -0,0 +1,49 @@
+use cas::{
+    content::{Address, AddressableContent, Content},
+    storage::ContentAddressableStorage,
+};
+use error::HolochainError;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// a `ContentAddressableStorage` that keeps everything in memory; nothing
+/// survives a restart, but it is cheap and deterministic for tests
+#[derive(Clone, Debug, Default)]
+pub struct MemoryCas {
+    storage: Arc<RwLock<HashMap<Address, Content>>>,
+}
+
+impl MemoryCas {
+    pub fn new() -> MemoryCas {
+        MemoryCas::default()
+    }
+}
+
+impl ContentAddressableStorage for MemoryCas {
+    fn add(&mut self, content: &dyn AddressableContent) -> Result<(), HolochainError> {
+        self.storage
+            .write()
+            .map_err(|e| HolochainError::new(&e.to_string()))?
+            .insert(content.address(), content.content());
+        Ok(())
+    }
+
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError> {
+        Ok(self
+            .storage
+            .read()
+            .map_err(|e| HolochainError::new(&e.to_string()))?
+            .contains_key(address))
+    }
+
+    fn fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError> {
+        Ok(self
+            .storage
+            .read()
+            .map_err(|e| HolochainError::new(&e.to_string()))?
+            .get(address)
+            .cloned())
+    }
+}
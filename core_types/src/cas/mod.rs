@@ -0,0 +1,8 @@
+//! Content-addressable storage: generic trait over "store bytes, fetch them
+//! back by the hash of their content" plus the implementations that satisfy
+//! it (an in-memory one for tests, a RocksDB-backed one for production).
+
+pub mod content;
+pub mod memory;
+pub mod rocks;
+pub mod storage;
@@ -0,0 +1,135 @@
+use cas::{
+    content::{Address, AddressableContent, Content},
+    storage::ContentAddressableStorage,
+};
+use error::HolochainError;
+use rocksdb::{ColumnFamilyDescriptor, Options, DB};
+use std::{path::Path, sync::Arc};
+
+const CF_CAS: &str = "cas";
+
+/// a `ContentAddressableStorage` backed by a RocksDB column family, so
+/// content written by one process run is still there on the next
+#[derive(Clone)]
+pub struct RocksCas {
+    db: Arc<DB>,
+}
+
+impl RocksCas {
+    /// open (or create) a RocksDB database at `path` with a dedicated `cas`
+    /// column family; `cache_size_mb` sizes its write buffer (memtable)
+    pub fn open<P: AsRef<Path>>(path: P, cache_size_mb: usize) -> Result<RocksCas, HolochainError> {
+        let mut cf_opts = Options::default();
+        cf_opts.set_write_buffer_size(cache_size_mb * 1024 * 1024);
+        let cf = ColumnFamilyDescriptor::new(CF_CAS, cf_opts);
+
+        let mut db_opts = Options::default();
+        db_opts.create_if_missing(true);
+        db_opts.create_missing_column_families(true);
+
+        let db = DB::open_cf_descriptors(&db_opts, path, vec![cf])
+            .map_err(|e| HolochainError::new(&e.to_string()))?;
+
+        Ok(RocksCas { db: Arc::new(db) })
+    }
+
+    /// force a compaction of the `cas` column family, e.g. after a large
+    /// bulk import
+    pub fn compact(&self) -> Result<(), HolochainError> {
+        let cf = self.cf()?;
+        self.db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+        Ok(())
+    }
+
+    /// flush the in-memory memtable for the `cas` column family to disk
+    pub fn flush(&self) -> Result<(), HolochainError> {
+        let cf = self.cf()?;
+        self.db
+            .flush_cf(cf)
+            .map_err(|e| HolochainError::new(&e.to_string()))
+    }
+
+    fn cf(&self) -> Result<&rocksdb::ColumnFamily, HolochainError> {
+        self.db
+            .cf_handle(CF_CAS)
+            .ok_or_else(|| HolochainError::new("cas column family missing"))
+    }
+}
+
+impl std::fmt::Debug for RocksCas {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RocksCas").finish()
+    }
+}
+
+impl ContentAddressableStorage for RocksCas {
+    fn add(&mut self, content: &dyn AddressableContent) -> Result<(), HolochainError> {
+        let cf = self.cf()?;
+        self.db
+            .put_cf(
+                cf,
+                content.address().to_string().as_bytes(),
+                content.content().as_bytes(),
+            )
+            .map_err(|e| HolochainError::new(&e.to_string()))
+    }
+
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError> {
+        Ok(self.fetch(address)?.is_some())
+    }
+
+    fn fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError> {
+        let cf = self.cf()?;
+        let bytes = self
+            .db
+            .get_cf(cf, address.to_string().as_bytes())
+            .map_err(|e| HolochainError::new(&e.to_string()))?;
+        Ok(bytes.map(|b| String::from_utf8_lossy(&b).into_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snowflake::ProcessUniqueId;
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct TestContent(String);
+
+    impl AddressableContent for TestContent {
+        fn content(&self) -> Content {
+            self.0.clone()
+        }
+
+        fn from_content(content: &Content) -> Self {
+            TestContent(content.clone())
+        }
+    }
+
+    fn test_db() -> RocksCas {
+        let path = std::env::temp_dir().join(format!("rocks_cas_test_{}", ProcessUniqueId::new()));
+        RocksCas::open(path, 1).expect("failed to open test RocksCas")
+    }
+
+    #[test]
+    fn it_round_trips_content_through_rocksdb() {
+        let mut cas = test_db();
+        let content = TestContent("round trip me".to_string());
+
+        assert_eq!(cas.contains(&content.address()).unwrap(), false);
+
+        cas.add(&content).unwrap();
+
+        assert_eq!(cas.contains(&content.address()).unwrap(), true);
+        assert_eq!(
+            cas.fetch(&content.address()).unwrap(),
+            Some(content.content())
+        );
+    }
+
+    #[test]
+    fn it_returns_none_for_missing_address() {
+        let cas = test_db();
+        assert_eq!(cas.fetch(&Address::new("nonexistent")).unwrap(), None);
+    }
+}
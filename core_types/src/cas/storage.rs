@@ -0,0 +1,14 @@
+use cas::content::{Address, AddressableContent, Content};
+use error::HolochainError;
+use std::fmt::Debug;
+
+/// something that can store and fetch content, keyed by its own address.
+/// `objekt::Clone` lets trait objects (`Box<dyn ContentAddressableStorage>`)
+/// be cloned the same way concrete storage structs are.
+pub trait ContentAddressableStorage: objekt::Clone + Send + Sync + Debug {
+    fn add(&mut self, content: &dyn AddressableContent) -> Result<(), HolochainError>;
+    fn contains(&self, address: &Address) -> Result<bool, HolochainError>;
+    fn fetch(&self, address: &Address) -> Result<Option<Content>, HolochainError>;
+}
+
+clone_trait_object!(ContentAddressableStorage);
@@ -0,0 +1,47 @@
+use cas::content::Address;
+use eav::{EntityAttributeValue, EntityAttributeValueStorage};
+use error::HolochainError;
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, RwLock},
+};
+
+/// an `EntityAttributeValueStorage` that keeps everything in memory
+#[derive(Clone, Debug, Default)]
+pub struct MemoryEav {
+    storage: Arc<RwLock<BTreeSet<EntityAttributeValue>>>,
+}
+
+impl MemoryEav {
+    pub fn new() -> MemoryEav {
+        MemoryEav::default()
+    }
+}
+
+impl EntityAttributeValueStorage for MemoryEav {
+    fn add_eav(&mut self, eav: &EntityAttributeValue) -> Result<(), HolochainError> {
+        self.storage
+            .write()
+            .map_err(|e| HolochainError::new(&e.to_string()))?
+            .insert(eav.clone());
+        Ok(())
+    }
+
+    fn fetch_eav(
+        &self,
+        entity: Option<Address>,
+        attribute: Option<String>,
+        value: Option<Address>,
+    ) -> Result<BTreeSet<EntityAttributeValue>, HolochainError> {
+        Ok(self
+            .storage
+            .read()
+            .map_err(|e| HolochainError::new(&e.to_string()))?
+            .iter()
+            .filter(|e| entity.as_ref().map_or(true, |a| *a == e.entity()))
+            .filter(|e| attribute.as_ref().map_or(true, |a| *a == e.attribute()))
+            .filter(|e| value.as_ref().map_or(true, |a| *a == e.value()))
+            .cloned()
+            .collect())
+    }
+}
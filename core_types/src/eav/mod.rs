@@ -0,0 +1,55 @@
+//! Entity-Attribute-Value storage: the generic trait over "index a triple of
+//! (entity address, attribute name, value address) so it can be looked up by
+//! any subset of the three" plus the implementations that satisfy it.
+
+pub mod memory;
+pub mod rocks;
+
+use cas::content::Address;
+use error::HolochainError;
+use std::collections::BTreeSet;
+
+/// one entity-attribute-value triple
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EntityAttributeValue {
+    entity: Address,
+    attribute: String,
+    value: Address,
+}
+
+impl EntityAttributeValue {
+    pub fn new(entity: &Address, attribute: &str, value: &Address) -> EntityAttributeValue {
+        EntityAttributeValue {
+            entity: entity.clone(),
+            attribute: attribute.to_string(),
+            value: value.clone(),
+        }
+    }
+
+    pub fn entity(&self) -> Address {
+        self.entity.clone()
+    }
+
+    pub fn attribute(&self) -> String {
+        self.attribute.clone()
+    }
+
+    pub fn value(&self) -> Address {
+        self.value.clone()
+    }
+}
+
+/// something that can index entity-attribute-value triples and answer
+/// queries over any subset of (entity, attribute, value)
+pub trait EntityAttributeValueStorage: objekt::Clone + Send + Sync + std::fmt::Debug {
+    fn add_eav(&mut self, eav: &EntityAttributeValue) -> Result<(), HolochainError>;
+
+    fn fetch_eav(
+        &self,
+        entity: Option<Address>,
+        attribute: Option<String>,
+        value: Option<Address>,
+    ) -> Result<BTreeSet<EntityAttributeValue>, HolochainError>;
+}
+
+clone_trait_object!(EntityAttributeValueStorage);
@@ -0,0 +1,235 @@
+use cas::content::Address;
+use eav::{EntityAttributeValue, EntityAttributeValueStorage};
+use error::HolochainError;
+use rocksdb::{ColumnFamilyDescriptor, Options, DB};
+use std::{collections::BTreeSet, path::Path, sync::Arc};
+
+const CF_EAV: &str = "eav";
+const SEP: u8 = 0;
+const ESC: u8 = 1;
+
+/// an `EntityAttributeValueStorage` backed by a RocksDB column family.
+/// Each triple is written under `entity \0 attribute \0 value` so that
+/// "all triples for this entity" queries are a cheap prefix scan rather
+/// than a full table scan. `SEP`/`ESC` bytes occurring inside a component
+/// are themselves escaped (see `encode_component`) so an attribute or
+/// address containing one can't be mistaken for a component boundary.
+#[derive(Clone)]
+pub struct RocksEav {
+    db: Arc<DB>,
+}
+
+impl RocksEav {
+    /// open (or create) a RocksDB database at `path` with a dedicated `eav`
+    /// column family; `cache_size_mb` sizes its write buffer (memtable)
+    pub fn open<P: AsRef<Path>>(path: P, cache_size_mb: usize) -> Result<RocksEav, HolochainError> {
+        let mut cf_opts = Options::default();
+        cf_opts.set_write_buffer_size(cache_size_mb * 1024 * 1024);
+        let cf = ColumnFamilyDescriptor::new(CF_EAV, cf_opts);
+
+        let mut db_opts = Options::default();
+        db_opts.create_if_missing(true);
+        db_opts.create_missing_column_families(true);
+
+        let db = DB::open_cf_descriptors(&db_opts, path, vec![cf])
+            .map_err(|e| HolochainError::new(&e.to_string()))?;
+
+        Ok(RocksEav { db: Arc::new(db) })
+    }
+
+    /// force a compaction of the `eav` column family, e.g. after a large
+    /// bulk import
+    pub fn compact(&self) -> Result<(), HolochainError> {
+        let cf = self.cf()?;
+        self.db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+        Ok(())
+    }
+
+    /// flush the in-memory memtable for the `eav` column family to disk
+    pub fn flush(&self) -> Result<(), HolochainError> {
+        let cf = self.cf()?;
+        self.db
+            .flush_cf(cf)
+            .map_err(|e| HolochainError::new(&e.to_string()))
+    }
+
+    fn cf(&self) -> Result<&rocksdb::ColumnFamily, HolochainError> {
+        self.db
+            .cf_handle(CF_EAV)
+            .ok_or_else(|| HolochainError::new("eav column family missing"))
+    }
+
+    /// escape any `SEP`/`ESC` byte in `component` so it can't be confused
+    /// with a component boundary once it's concatenated into a key
+    fn encode_component(component: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(component.len());
+        for &b in component {
+            if b == SEP || b == ESC {
+                out.push(ESC);
+            }
+            out.push(b);
+        }
+        out
+    }
+
+    fn key(eav: &EntityAttributeValue) -> Vec<u8> {
+        let mut key = RocksEav::encode_component(eav.entity().to_string().as_bytes());
+        key.push(SEP);
+        key.extend(RocksEav::encode_component(eav.attribute().as_bytes()));
+        key.push(SEP);
+        key.extend(RocksEav::encode_component(eav.value().to_string().as_bytes()));
+        key
+    }
+
+    /// split `key` back into its entity/attribute/value components on
+    /// unescaped `SEP` bytes, undoing `encode_component`'s escaping
+    fn decode_components(key: &[u8]) -> Vec<Vec<u8>> {
+        let mut components = Vec::new();
+        let mut current = Vec::new();
+        let mut bytes = key.iter().copied();
+
+        while let Some(b) = bytes.next() {
+            if b == ESC {
+                if let Some(escaped) = bytes.next() {
+                    current.push(escaped);
+                }
+            } else if b == SEP {
+                components.push(std::mem::replace(&mut current, Vec::new()));
+            } else {
+                current.push(b);
+            }
+        }
+        components.push(current);
+        components
+    }
+
+    fn decode(key: &[u8]) -> Option<EntityAttributeValue> {
+        let mut parts = RocksEav::decode_components(key).into_iter();
+        let entity = parts.next()?;
+        let attribute = parts.next()?;
+        let value = parts.next()?;
+        Some(EntityAttributeValue::new(
+            &Address::from(String::from_utf8_lossy(&entity).into_owned()),
+            &String::from_utf8_lossy(&attribute),
+            &Address::from(String::from_utf8_lossy(&value).into_owned()),
+        ))
+    }
+}
+
+impl std::fmt::Debug for RocksEav {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RocksEav").finish()
+    }
+}
+
+impl EntityAttributeValueStorage for RocksEav {
+    fn add_eav(&mut self, eav: &EntityAttributeValue) -> Result<(), HolochainError> {
+        let cf = self.cf()?;
+        self.db
+            .put_cf(cf, RocksEav::key(eav), [])
+            .map_err(|e| HolochainError::new(&e.to_string()))
+    }
+
+    fn fetch_eav(
+        &self,
+        entity: Option<Address>,
+        attribute: Option<String>,
+        value: Option<Address>,
+    ) -> Result<BTreeSet<EntityAttributeValue>, HolochainError> {
+        let cf = self.cf()?;
+
+        // an entity is always the first key component, so when it is known
+        // we can scan only its prefix instead of the whole column family
+        let prefix: Vec<u8> = match &entity {
+            Some(e) => {
+                let mut p = RocksEav::encode_component(e.to_string().as_bytes());
+                p.push(SEP);
+                p
+            }
+            None => Vec::new(),
+        };
+
+        let mut results = BTreeSet::new();
+        let iter = self.db.prefix_iterator_cf(cf, &prefix);
+        for (key, _) in iter {
+            // the iterator seeks to `prefix` but does not stop at its end,
+            // and keys are sorted, so the first key outside it ends the scan
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            let eav = match RocksEav::decode(&key) {
+                Some(eav) => eav,
+                None => continue,
+            };
+            if attribute.as_ref().map_or(false, |a| *a != eav.attribute()) {
+                continue;
+            }
+            if value.as_ref().map_or(false, |v| *v != eav.value()) {
+                continue;
+            }
+            results.insert(eav);
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use snowflake::ProcessUniqueId;
+
+    fn test_db() -> RocksEav {
+        let path = std::env::temp_dir().join(format!("rocks_eav_test_{}", ProcessUniqueId::new()));
+        RocksEav::open(path, 1).expect("failed to open test RocksEav")
+    }
+
+    #[test]
+    fn it_round_trips_and_queries_by_entity_prefix() {
+        let mut eav = test_db();
+        let entity = Address::new("entity_1");
+        let other_entity = Address::new("entity_2");
+
+        let a = EntityAttributeValue::new(&entity, "link", &Address::new("value_a"));
+        let b = EntityAttributeValue::new(&entity, "link", &Address::new("value_b"));
+        let c = EntityAttributeValue::new(&other_entity, "link", &Address::new("value_c"));
+
+        eav.add_eav(&a).unwrap();
+        eav.add_eav(&b).unwrap();
+        eav.add_eav(&c).unwrap();
+
+        let for_entity = eav.fetch_eav(Some(entity), None, None).unwrap();
+        assert_eq!(for_entity.len(), 2);
+        assert!(for_entity.contains(&a));
+        assert!(for_entity.contains(&b));
+    }
+
+    #[test]
+    fn it_round_trips_an_attribute_containing_an_embedded_nul_byte() {
+        let mut eav = test_db();
+        let entity = Address::new("entity_1");
+        let tricky = EntityAttributeValue::new(&entity, "attr\0ibute", &Address::new("value_a"));
+
+        eav.add_eav(&tricky).unwrap();
+
+        let for_entity = eav.fetch_eav(Some(entity), None, None).unwrap();
+        assert_eq!(for_entity.len(), 1);
+        assert!(for_entity.contains(&tricky));
+    }
+
+    #[test]
+    fn it_filters_by_value() {
+        let mut eav = test_db();
+        let entity = Address::new("entity_1");
+        let a = EntityAttributeValue::new(&entity, "link", &Address::new("value_a"));
+        let b = EntityAttributeValue::new(&entity, "link", &Address::new("value_b"));
+
+        eav.add_eav(&a).unwrap();
+        eav.add_eav(&b).unwrap();
+
+        let filtered = eav
+            .fetch_eav(Some(entity), None, Some(Address::new("value_a")))
+            .unwrap();
+        assert_eq!(filtered, vec![a].into_iter().collect());
+    }
+}
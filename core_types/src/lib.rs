@@ -16,6 +16,7 @@ extern crate base64;
 extern crate futures;
 extern crate multihash;
 extern crate reed_solomon;
+extern crate rocksdb;
 extern crate rust_base58;
 extern crate serde;
 #[macro_use]
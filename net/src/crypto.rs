@@ -0,0 +1,143 @@
+//! optional AEAD-encrypted transport for `Protocol` frames exchanged with
+//! the IPC relay. Disabled by default; enabled per-worker via the
+//! `"encryption": "aes-gcm"` config flag so plaintext mode remains the
+//! default for the mock/test sockets.
+//!
+//! The ephemeral X25519 public key each side publishes during the handshake
+//! is signed with the local agent's long-term key (`HandshakeState::sign_public_key`)
+//! and verified against the peer's known agent id before the session is
+//! derived (`verify_public_key`). Binding the DH exchange to agent identity
+//! this way is what makes it resistant to an active MITM: an attacker can't
+//! produce a valid signature over a substituted key without the real agent's
+//! private key, so a forged `encryptionPublicKey` fails verification instead
+//! of silently being accepted.
+
+extern crate rand;
+extern crate x25519_dalek;
+
+use self::{
+    rand::{rngs::OsRng, RngCore},
+    x25519_dalek::{EphemeralSecret, PublicKey},
+};
+use aes_gcm::{
+    aead::{generic_array::GenericArray, Aead, NewAead},
+    Aes256Gcm,
+};
+use holochain_core_types::{keys::Keys, signature::Signature};
+
+use NetResult;
+
+extern crate base64;
+
+const NONCE_LEN: usize = 12;
+
+/// Parse a 32-byte ephemeral X25519 public key out of its wire (base64)
+/// representation, as received in the peer's side of the handshake.
+pub fn decode_public_key(base64_key: &str) -> NetResult<PublicKey> {
+    let bytes = base64::decode(base64_key).map_err(|_| "invalid base64 public key")?;
+    if bytes.len() != 32 {
+        bail!("expected a 32-byte public key, got {}", bytes.len());
+    }
+    let mut arr = [0u8; 32];
+    arr.copy_from_slice(&bytes);
+    Ok(PublicKey::from(arr))
+}
+
+/// One side of the ephemeral X25519 key exchange performed during the
+/// `need_config` -> `RequestDefaultConfig` -> `SetConfig` handshake.
+pub struct HandshakeState {
+    secret: Option<EphemeralSecret>,
+    pub public_key: PublicKey,
+}
+
+impl HandshakeState {
+    pub fn new() -> Self {
+        let secret = EphemeralSecret::new(&mut OsRng);
+        let public_key = PublicKey::from(&secret);
+        HandshakeState {
+            secret: Some(secret),
+            public_key,
+        }
+    }
+
+    /// Base64-encoded ephemeral public key, suitable for embedding in the
+    /// JSON config negotiated during the handshake.
+    pub fn public_key_base64(&self) -> String {
+        base64::encode(self.public_key.as_bytes())
+    }
+
+    /// sign this handshake's ephemeral public key with the local agent's
+    /// long-term key, so the peer can verify (via `verify_public_key`) that
+    /// it really came from us and not from an active MITM sitting on the DH
+    /// exchange.
+    pub fn sign_public_key(&self, agent_keys: &Keys) -> NetResult<Signature> {
+        agent_keys
+            .sign(&self.public_key_base64())
+            .map_err(|e| format!("failed to sign handshake public key: {}", e).into())
+    }
+
+    /// Consume the handshake, deriving the shared AES-256-GCM session cipher
+    /// from the peer's ephemeral public key.
+    pub fn derive_session(mut self, peer_public_key: &PublicKey) -> NetResult<SessionCrypto> {
+        let secret = self
+            .secret
+            .take()
+            .ok_or_else(|| "handshake already completed")?;
+        let shared = secret.diffie_hellman(peer_public_key);
+        let key = GenericArray::clone_from_slice(shared.as_bytes());
+        Ok(SessionCrypto {
+            cipher: Aes256Gcm::new(key),
+        })
+    }
+}
+
+/// verify that `peer_public_key_base64` was actually signed by
+/// `peer_agent_id`'s long-term key, so a key substituted by an active MITM
+/// is rejected before a session ever gets derived from it. Must be called
+/// before `derive_session` for the exchange to be anything more than
+/// passive-eavesdropping protection.
+pub fn verify_public_key(
+    peer_agent_id: &str,
+    peer_public_key_base64: &str,
+    signature: &Signature,
+) -> NetResult<()> {
+    let valid = Keys::verify(peer_agent_id, peer_public_key_base64, signature)
+        .map_err(|e| format!("failed to verify peer's handshake signature: {}", e))?;
+    if !valid {
+        bail!("peer's handshake public key signature does not match its agent id - possible MITM");
+    }
+    Ok(())
+}
+
+/// A negotiated AES-256-GCM session used to seal/open outbound and inbound
+/// `Protocol` frames. The wire format is a 12-byte random nonce prepended to
+/// the ciphertext + tag.
+pub struct SessionCrypto {
+    cipher: Aes256Gcm,
+}
+
+impl SessionCrypto {
+    pub fn seal(&self, plaintext: &[u8]) -> NetResult<Vec<u8>> {
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce = GenericArray::from_slice(&nonce_bytes);
+        let mut out = nonce_bytes.to_vec();
+        out.extend(
+            self.cipher
+                .encrypt(nonce, plaintext)
+                .map_err(|_| "AEAD encryption failed")?,
+        );
+        Ok(out)
+    }
+
+    pub fn open(&self, envelope: &[u8]) -> NetResult<Vec<u8>> {
+        if envelope.len() < NONCE_LEN {
+            bail!("encrypted frame too short");
+        }
+        let (nonce_bytes, ciphertext) = envelope.split_at(NONCE_LEN);
+        let nonce = GenericArray::from_slice(nonce_bytes);
+        self.cipher
+            .decrypt(nonce, ciphertext)
+            .map_err(|_| "AEAD decryption failed".into())
+    }
+}
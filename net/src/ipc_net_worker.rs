@@ -1,6 +1,6 @@
 //! provides a NetWorker implementation for backend IPC p2p connections
 
-use holochain_core_types::json::JsonString;
+use holochain_core_types::{json::JsonString, keys::Keys, signature::Signature};
 
 use holochain_net_ipc::{
     ipc_client::IpcClient,
@@ -8,28 +8,75 @@ use holochain_net_ipc::{
     util::get_millis,
 };
 
+use named_pipe_socket::NamedPipeIpcSocket;
+use router::Router;
+
 use holochain_net_connection::{
     net_connection::{NetConnection, NetConnectionRelay, NetHandler, NetWorker, NetWorkerFactory},
-    protocol::Protocol,
+    protocol::{NamedBinaryData, Protocol},
     protocol_wrapper::{ConfigData, ProtocolWrapper, StateData},
     NetResult,
 };
 
-use std::{convert::TryFrom, sync::mpsc};
+use std::{
+    convert::TryFrom,
+    sync::{mpsc, Arc},
+};
 
 use serde_json;
 
+use crypto::{self, HandshakeState, SessionCrypto};
+
+const RECONNECT_INITIAL_BACKOFF_MILLIS: f64 = 250.0;
+const RECONNECT_MAX_BACKOFF_MILLIS: f64 = 30_000.0;
+
 /// a p2p net worker
 pub struct IpcNetWorker {
     handler: NetHandler,
     ipc_relay: NetConnectionRelay,
     ipc_relay_receiver: mpsc::Receiver<Protocol>,
 
+    /// kept around so a dropped relay can be rebuilt from scratch on reconnect
+    factory: Arc<NetWorkerFactory>,
+
+    /// stamps correlation ids onto requests made via `call`, addresses
+    /// `broadcast` copies to each of the peer's reported bindings and tracks
+    /// those bindings; every inbound frame is run
+    /// through it before being handed to `handler` so in-flight `call`s get
+    /// resolved
+    router: Router,
+
     is_ready: bool,
 
     state: String,
 
     last_state_millis: f64,
+
+    /// consecutive send/recv failures since the last successful `ready`
+    /// handshake; drives the reconnect backoff below
+    consecutive_failures: u32,
+    reconnect_backoff_millis: f64,
+    last_reconnect_attempt_millis: f64,
+
+    /// set once via `new`'s config `"encryption": "aes-gcm"` flag; when set,
+    /// a session is negotiated during the config handshake; after that,
+    /// frames passed to `receive` are sealed and every inbound frame is
+    /// opened through it. Frames the worker sends on its own (state pings,
+    /// config replies) and frames sent via `call`/`broadcast` are not
+    /// sealed. The
+    /// exchanged ephemeral key is signed with `keys` and verified against
+    /// `peer_agent_id` (see `priv_negotiate_encryption`), binding the
+    /// session to agent identity so an active MITM can't substitute its own
+    /// key into the DH exchange.
+    encryption_requested: bool,
+    handshake: Option<HandshakeState>,
+    session: Option<SessionCrypto>,
+
+    /// the local agent's signing key and the peer's agent id, required when
+    /// `encryption_requested` is set so the handshake can be authenticated;
+    /// `new` rejects an encryption request made without both.
+    keys: Option<Keys>,
+    peer_agent_id: Option<String>,
 }
 
 impl NetWorker for IpcNetWorker {
@@ -42,7 +89,10 @@ impl NetWorker for IpcNetWorker {
     /// we got a message from holochain core
     /// (just forwards to the internal worker relay)
     fn receive(&mut self, data: Protocol) -> NetResult<()> {
-        self.ipc_relay.send(data)?;
+        let sealed = self.priv_seal(data)?;
+        if self.ipc_relay.send(sealed).is_err() {
+            return self.priv_handle_failure();
+        }
         Ok(())
     }
 
@@ -54,12 +104,16 @@ impl NetWorker for IpcNetWorker {
             self.priv_check_init()?;
         }
 
-        if self.ipc_relay.tick()? {
-            did_something = true;
+        match self.ipc_relay.tick() {
+            Ok(true) => did_something = true,
+            Ok(false) => (),
+            Err(_) => return self.priv_handle_failure().map(|_| did_something),
         }
 
         if let Ok(data) = self.ipc_relay_receiver.try_recv() {
             did_something = true;
+            let data = self.priv_open(data)?;
+            let data = self.router.resolve(data);
 
             if let Ok(wrap) = ProtocolWrapper::try_from(&data) {
                 match wrap {
@@ -95,66 +149,230 @@ impl IpcNetWorker {
                 let out: Box<NetWorker> = Box::new(IpcClient::new(h, socket, true)?);
                 Ok(out)
             }),
+            false,
+            None,
+            None,
         )
     }
 
-    pub fn new(handler: NetHandler, config: &JsonString) -> NetResult<Self> {
+    /// `keys` is the local agent's signing key, used to prove ownership of
+    /// the ephemeral key offered during an encrypted handshake; required
+    /// (together with `config.peerAgentId`) whenever
+    /// `config.encryption == "aes-gcm"` so the session can be bound to
+    /// verified agent identity instead of accepting any peer's key.
+    pub fn new(handler: NetHandler, config: &JsonString, keys: Option<Keys>) -> NetResult<Self> {
         let config: serde_json::Value = serde_json::from_str(config.into())?;
-        if config["socketType"] != "zmq" {
-            bail!("unexpected socketType: {}", config["socketType"]);
-        }
-        if let None = config["ipcUri"].as_str() {
-            bail!("config.ipcUri is required");
-        }
-        let uri = config["ipcUri"].as_str().unwrap().to_string();
         let mut block_connect = false;
         if let Some(b) = config["blockConnect"].as_bool() {
             block_connect = b;
         }
-        IpcNetWorker::priv_new(
-            handler,
-            Box::new(move |h| {
-                let mut socket = ZmqIpcSocket::new()?;
-                socket.connect(&uri)?;
-                let out: Box<NetWorker> = Box::new(IpcClient::new(h, socket, block_connect)?);
-                Ok(out)
-            }),
-        )
+        let encryption_requested = config["encryption"].as_str() == Some("aes-gcm");
+        let peer_agent_id = config["peerAgentId"].as_str().map(|s| s.to_string());
+
+        if encryption_requested && (keys.is_none() || peer_agent_id.is_none()) {
+            bail!(
+                "config.encryption requires both an agent signing key and config.peerAgentId \
+                 so the handshake can be authenticated"
+            );
+        }
+
+        match config["socketType"].as_str() {
+            Some("zmq") => {
+                if let None = config["ipcUri"].as_str() {
+                    bail!("config.ipcUri is required");
+                }
+                let uri = config["ipcUri"].as_str().unwrap().to_string();
+                IpcNetWorker::priv_new(
+                    handler,
+                    Box::new(move |h| {
+                        let mut socket = ZmqIpcSocket::new()?;
+                        socket.connect(&uri)?;
+                        let out: Box<NetWorker> = Box::new(IpcClient::new(h, socket, block_connect)?);
+                        Ok(out)
+                    }),
+                    encryption_requested,
+                    keys,
+                    peer_agent_id,
+                )
+            }
+            Some("named_pipe") => {
+                let uri = config["pipeName"]
+                    .as_str()
+                    .or_else(|| config["ipcUri"].as_str())
+                    .ok_or_else(|| "config.pipeName (or ipcUri) is required")?
+                    .to_string();
+                IpcNetWorker::priv_new(
+                    handler,
+                    Box::new(move |h| {
+                        let mut socket = NamedPipeIpcSocket::new()?;
+                        socket.connect(&uri)?;
+                        let out: Box<NetWorker> = Box::new(IpcClient::new(h, socket, block_connect)?);
+                        Ok(out)
+                    }),
+                    encryption_requested,
+                    keys,
+                    peer_agent_id,
+                )
+            }
+            _ => bail!("unexpected socketType: {}", config["socketType"]),
+        }
+    }
+
+    /// issue `msg` as a correlated request and return a receiver that
+    /// resolves with the matching response once it comes back through a
+    /// later `tick()` (see `Router::request`)
+    pub fn call(&mut self, msg: Protocol) -> NetResult<mpsc::Receiver<Protocol>> {
+        self.router.request(&mut self.ipc_relay, msg)
+    }
+
+    /// fan `msg` out to every binding the peer reported in its last
+    /// `StateData` (see `Router::broadcast`)
+    pub fn broadcast(&mut self, msg: Protocol) -> NetResult<()> {
+        self.router.broadcast(&mut self.ipc_relay, msg)
     }
 
     // -- private -- //
 
     /// create a new IpcNetWorker instance
-    fn priv_new(handler: NetHandler, factory: NetWorkerFactory) -> NetResult<Self> {
-        let (ipc_sender, ipc_relay_receiver) = mpsc::channel::<Protocol>();
-
-        let ipc_relay = NetConnectionRelay::new(
-            Box::new(move |r| {
-                ipc_sender.send(r?)?;
-                Ok(())
-            }),
-            factory,
-        )?;
+    fn priv_new(
+        handler: NetHandler,
+        factory: NetWorkerFactory,
+        encryption_requested: bool,
+        keys: Option<Keys>,
+        peer_agent_id: Option<String>,
+    ) -> NetResult<Self> {
+        let factory = Arc::new(factory);
+        let (ipc_relay, ipc_relay_receiver) = IpcNetWorker::priv_build_relay(&factory)?;
 
         Ok(IpcNetWorker {
             handler,
             ipc_relay,
             ipc_relay_receiver,
+            factory,
+            router: Router::new(),
 
             is_ready: false,
 
             state: "undefined".to_string(),
 
             last_state_millis: 0.0_f64,
+
+            consecutive_failures: 0,
+            reconnect_backoff_millis: RECONNECT_INITIAL_BACKOFF_MILLIS,
+            last_reconnect_attempt_millis: 0.0_f64,
+
+            encryption_requested,
+            handshake: None,
+            session: None,
+
+            keys,
+            peer_agent_id,
         })
     }
 
+    /// build a fresh `NetConnectionRelay` (and its receiver) out of the
+    /// stored `NetWorkerFactory`, used both on construction and reconnect
+    fn priv_build_relay(
+        factory: &Arc<NetWorkerFactory>,
+    ) -> NetResult<(NetConnectionRelay, mpsc::Receiver<Protocol>)> {
+        let (ipc_sender, ipc_relay_receiver) = mpsc::channel::<Protocol>();
+        let factory = factory.clone();
+
+        let ipc_relay = NetConnectionRelay::new(
+            Box::new(move |r| {
+                ipc_sender.send(r?)?;
+                Ok(())
+            }),
+            Box::new(move |h| (factory)(h)),
+        )?;
+
+        Ok((ipc_relay, ipc_relay_receiver))
+    }
+
+    /// record a send/recv failure and, once the backoff has elapsed, rebuild
+    /// the relay from scratch; resets on the next successful `ready` handshake.
+    ///
+    /// The backoff grows on every reconnect attempt regardless of whether
+    /// rebuilding the relay itself succeeds or the peer never completes the
+    /// handshake afterwards — only a successful `ready` state resets it (see
+    /// `priv_handle_recovered`). `connectionLost` fires once, the moment a
+    /// previously-ready link goes down, not on every rebuild attempt that
+    /// follows while it stays down.
+    fn priv_handle_failure(&mut self) -> NetResult<()> {
+        self.consecutive_failures += 1;
+        self.state = "reconnecting".to_string();
+
+        let was_ready = self.is_ready;
+        self.is_ready = false;
+
+        if was_ready {
+            (self.handler)(Ok(Protocol::Json(
+                json!({ "method": "connectionLost" }).into(),
+            )))?;
+        }
+
+        let now = get_millis();
+        if now - self.last_reconnect_attempt_millis < self.reconnect_backoff_millis {
+            return Ok(());
+        }
+        self.last_reconnect_attempt_millis = now;
+        self.reconnect_backoff_millis =
+            (self.reconnect_backoff_millis * 2.0).min(RECONNECT_MAX_BACKOFF_MILLIS);
+
+        if let Ok((ipc_relay, ipc_relay_receiver)) = IpcNetWorker::priv_build_relay(&self.factory)
+        {
+            self.ipc_relay = ipc_relay;
+            self.ipc_relay_receiver = ipc_relay_receiver;
+            self.last_state_millis = 0.0_f64;
+        }
+
+        Ok(())
+    }
+
+    /// a successful `ready` handshake means the link is healthy again
+    fn priv_handle_recovered(&mut self) -> NetResult<()> {
+        if self.consecutive_failures > 0 {
+            (self.handler)(Ok(Protocol::Json(
+                json!({ "method": "connectionRestored" }).into(),
+            )))?;
+        }
+        self.consecutive_failures = 0;
+        self.reconnect_backoff_millis = RECONNECT_INITIAL_BACKOFF_MILLIS;
+        Ok(())
+    }
+
+    /// seal an outbound frame through the negotiated session, if any
+    fn priv_seal(&self, data: Protocol) -> NetResult<Protocol> {
+        match &self.session {
+            Some(session) => {
+                let plaintext: NamedBinaryData = (&data).into();
+                let sealed = session.seal(&plaintext.data)?;
+                Ok((&NamedBinaryData { data: sealed }).into())
+            }
+            None => Ok(data),
+        }
+    }
+
+    /// open an inbound frame through the negotiated session, if any
+    fn priv_open(&self, data: Protocol) -> NetResult<Protocol> {
+        match &self.session {
+            Some(session) => {
+                let sealed: NamedBinaryData = (&data).into();
+                let opened = session.open(&sealed.data)?;
+                Ok((&NamedBinaryData { data: opened }).into())
+            }
+            None => Ok(data),
+        }
+    }
+
     /// send a ping twice per second
     fn priv_check_init(&mut self) -> NetResult<()> {
         let now = get_millis();
 
         if now - self.last_state_millis > 500.0 {
-            self.ipc_relay.send(ProtocolWrapper::RequestState.into())?;
+            if self.ipc_relay.send(ProtocolWrapper::RequestState.into()).is_err() {
+                return self.priv_handle_failure();
+            }
             self.last_state_millis = now;
         }
 
@@ -163,8 +381,13 @@ impl IpcNetWorker {
 
     /// if the internal worker needs configuration, fetch the default config
     fn priv_handle_state(&mut self, state: StateData) -> NetResult<()> {
+        self.router.note_state(&state);
         self.state = state.state;
 
+        if &self.state == "ready" {
+            self.priv_handle_recovered()?;
+        }
+
         if &self.state == "need_config" {
             self.ipc_relay
                 .send(ProtocolWrapper::RequestDefaultConfig.into())?;
@@ -184,23 +407,67 @@ impl IpcNetWorker {
     /// pass it back the default config
     fn priv_handle_default_config(&mut self, config: ConfigData) -> NetResult<()> {
         if &self.state == "need_config" {
-            self.ipc_relay.send(
-                ProtocolWrapper::SetConfig(ConfigData {
-                    config: config.config,
-                })
-                .into(),
-            )?;
+            let mut config = config;
+
+            if self.encryption_requested && self.session.is_none() {
+                config.config = self.priv_negotiate_encryption(&config.config)?;
+            }
+
+            self.ipc_relay
+                .send(ProtocolWrapper::SetConfig(config).into())?;
         }
 
         Ok(())
     }
+
+    /// embed our signed ephemeral public key into the outgoing config,
+    /// verifying the peer's signed key and deriving the shared AES-256-GCM
+    /// session once it has published its own. `new` already guarantees
+    /// `keys`/`peer_agent_id` are set whenever encryption was requested, so
+    /// both are trusted to be present here.
+    fn priv_negotiate_encryption(&mut self, config: &str) -> NetResult<String> {
+        let mut config_json: serde_json::Value =
+            serde_json::from_str(config).unwrap_or_else(|_| json!({}));
+
+        let keys = self
+            .keys
+            .as_ref()
+            .ok_or_else(|| "encryption requested with no agent signing key")?;
+        let peer_agent_id = self
+            .peer_agent_id
+            .as_ref()
+            .ok_or_else(|| "encryption requested with no config.peerAgentId")?;
+
+        if let Some(peer_key) = config_json["encryptionPublicKey"].as_str() {
+            let peer_signature: Signature = config_json["encryptionPublicKeySignature"]
+                .as_str()
+                .ok_or_else(|| "peer's handshake key is missing its signature")?
+                .into();
+            crypto::verify_public_key(peer_agent_id, peer_key, &peer_signature)?;
+
+            let peer_public_key = crypto::decode_public_key(peer_key)?;
+            let handshake = self.handshake.take().unwrap_or_else(HandshakeState::new);
+            config_json["encryptionPublicKey"] = json!(handshake.public_key_base64());
+            config_json["encryptionPublicKeySignature"] =
+                json!(handshake.sign_public_key(keys)?.to_string());
+            self.session = Some(handshake.derive_session(&peer_public_key)?);
+        } else {
+            let handshake = HandshakeState::new();
+            config_json["encryptionPublicKey"] = json!(handshake.public_key_base64());
+            config_json["encryptionPublicKeySignature"] =
+                json!(handshake.sign_public_key(keys)?.to_string());
+            self.handshake = Some(handshake);
+        }
+
+        Ok(config_json.to_string())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use holochain_net_connection::protocol::{NamedBinaryData, PongData};
+    use holochain_net_connection::protocol::PongData;
 
     use holochain_net_ipc::socket::make_test_channels;
 
@@ -214,8 +481,72 @@ mod tests {
             "blockConnect": false
         })
             .into(),
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn it_ipc_networker_zmq_create_with_encryption() {
+        let worker = IpcNetWorker::new(
+            Box::new(|_r| Ok(())),
+            &json!({
+            "socketType": "zmq",
+            "ipcUri": "tcp://127.0.0.1:0",
+            "blockConnect": false,
+            "encryption": "aes-gcm",
+            "peerAgentId": "test_peer_agent_id"
+        })
+            .into(),
+            Some(Keys::new()),
         )
         .unwrap();
+
+        assert!(worker.encryption_requested);
+        assert!(worker.session.is_none());
+    }
+
+    #[test]
+    fn it_ipc_networker_encryption_without_keys_is_rejected() {
+        let res = IpcNetWorker::new(
+            Box::new(|_r| Ok(())),
+            &json!({
+            "socketType": "zmq",
+            "ipcUri": "tcp://127.0.0.1:0",
+            "blockConnect": false,
+            "encryption": "aes-gcm"
+        })
+            .into(),
+            None,
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn it_ipc_networker_unknown_socket_type() {
+        let res = IpcNetWorker::new(
+            Box::new(|_r| Ok(())),
+            &json!({
+            "socketType": "carrier_pigeon",
+            "ipcUri": "tcp://127.0.0.1:0"
+        })
+            .into(),
+            None,
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn it_ipc_networker_named_pipe_missing_uri() {
+        let res = IpcNetWorker::new(
+            Box::new(|_r| Ok(())),
+            &json!({
+            "socketType": "named_pipe"
+        })
+            .into(),
+            None,
+        );
+        assert!(res.is_err());
     }
 
     #[test]
@@ -318,4 +649,33 @@ mod tests {
 
         cli.stop().unwrap();
     }
+
+    #[test]
+    fn it_ipc_networker_starts_with_no_reconnect_backoff() {
+        let cli = IpcNetWorker::new_test(Box::new(|_r| Ok(())), make_test_channels().unwrap().0)
+            .unwrap();
+
+        assert_eq!(cli.consecutive_failures, 0);
+        assert_eq!(cli.reconnect_backoff_millis, RECONNECT_INITIAL_BACKOFF_MILLIS);
+    }
+
+    #[test]
+    fn it_ipc_networker_doubles_backoff_on_repeated_failure() {
+        let mut cli =
+            IpcNetWorker::new_test(Box::new(|_r| Ok(())), make_test_channels().unwrap().0)
+                .unwrap();
+
+        // force the rebuild branch to fail every time by handing it a
+        // factory that always errors
+        cli.factory = Arc::new(Box::new(|_h| Err("simulated connect failure".into())));
+
+        cli.priv_handle_failure().unwrap();
+        let first_backoff = cli.reconnect_backoff_millis;
+        cli.last_reconnect_attempt_millis = 0.0;
+        cli.priv_handle_failure().unwrap();
+
+        assert_eq!(cli.state, "reconnecting");
+        assert!(cli.reconnect_backoff_millis > first_backoff);
+        assert!(cli.reconnect_backoff_millis <= RECONNECT_MAX_BACKOFF_MILLIS);
+    }
 }
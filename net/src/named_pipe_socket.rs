@@ -0,0 +1,283 @@
+//! a local-transport IpcSocket implementation backed by OS named pipes
+//! (named pipes on Windows, unix domain sockets as the POSIX fallback)
+
+use holochain_net_ipc::socket::IpcSocket;
+
+use NetResult;
+
+const LEN_PREFIX_BYTES: usize = 4;
+
+/// hard ceilings on a single framed message so a corrupt or adversarial peer
+/// can't make us allocate an unbounded amount of memory off of 4 bytes it
+/// controls, before a single length-prefix has even been range-checked
+/// against what's actually in the buffer
+const MAX_FRAMES_PER_MESSAGE: usize = 1024;
+const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+
+/// wire-encode a multi-frame message as a frame count followed by each
+/// frame's own length-prefix + bytes, so a reader can tell where one
+/// message ends and the next begins on a byte stream that has no framing
+/// of its own (unlike ZMQ's native multipart messages).
+fn encode_message(data: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    for frame in data {
+        out.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        out.extend_from_slice(frame);
+    }
+    out
+}
+
+/// try to pull one complete framed message out of the front of `buf`,
+/// leaving any trailing partial data in place for the next call. `buf`
+/// persists across calls so a message split across multiple non-blocking
+/// reads is reassembled instead of dropped.
+fn try_decode_message(buf: &mut Vec<u8>) -> NetResult<Option<Vec<Vec<u8>>>> {
+    if buf.len() < LEN_PREFIX_BYTES {
+        return Ok(None);
+    }
+    let frame_count = read_u32(&buf[0..LEN_PREFIX_BYTES]) as usize;
+    if frame_count > MAX_FRAMES_PER_MESSAGE {
+        bail!(
+            "framed message claims {} frames, more than the {} allowed",
+            frame_count,
+            MAX_FRAMES_PER_MESSAGE
+        );
+    }
+
+    let mut offset = LEN_PREFIX_BYTES;
+    let mut total_len = 0usize;
+    let mut frames = Vec::with_capacity(frame_count);
+    for _ in 0..frame_count {
+        if buf.len() < offset + LEN_PREFIX_BYTES {
+            return Ok(None);
+        }
+        let len = read_u32(&buf[offset..offset + LEN_PREFIX_BYTES]) as usize;
+        offset += LEN_PREFIX_BYTES;
+
+        total_len += len;
+        if total_len > MAX_MESSAGE_BYTES {
+            bail!(
+                "framed message is at least {} bytes, more than the {} allowed",
+                total_len,
+                MAX_MESSAGE_BYTES
+            );
+        }
+
+        if buf.len() < offset + len {
+            return Ok(None);
+        }
+        frames.push(buf[offset..offset + len].to_vec());
+        offset += len;
+    }
+
+    let remainder = buf.split_off(offset);
+    *buf = remainder;
+    Ok(Some(frames))
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::*;
+    use std::{
+        fs::{File, OpenOptions},
+        io::{Read, Write},
+    };
+
+    /// Windows named pipe transport (`\\.\pipe\...`). `OpenOptions::open`
+    /// against the pipe path is the client-side `CreateFile` call that
+    /// connects to a pipe instance some other process is already listening
+    /// on; `miow::pipe::NamedPipe::new` instead creates and listens on a
+    /// *new* server instance at that path, which is the wrong end of the
+    /// connection for a worker that's dialing out to an existing IPC server.
+    ///
+    /// Unlike the POSIX half, a pipe handle opened this way is a plain
+    /// blocking file handle — Windows doesn't give synchronous, non-blocking
+    /// reads the way `UnixStream::set_nonblocking` does, so `recv` can block
+    /// until the peer writes rather than returning `Ok(None)` promptly.
+    pub struct NamedPipeIpcSocket {
+        stream: Option<File>,
+        read_buf: Vec<u8>,
+    }
+
+    impl NamedPipeIpcSocket {
+        pub fn new() -> NetResult<Self> {
+            Ok(NamedPipeIpcSocket {
+                stream: None,
+                read_buf: Vec::new(),
+            })
+        }
+    }
+
+    impl IpcSocket for NamedPipeIpcSocket {
+        fn connect(&mut self, pipe_name: &str) -> NetResult<()> {
+            self.stream = Some(OpenOptions::new().read(true).write(true).open(pipe_name)?);
+            Ok(())
+        }
+
+        fn close(&mut self) -> NetResult<()> {
+            self.stream = None;
+            self.read_buf.clear();
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            self.stream.is_some()
+        }
+
+        fn send(&mut self, data: &[Vec<u8>]) -> NetResult<()> {
+            let stream = self.stream.as_mut().ok_or_else(|| "not connected")?;
+            stream.write_all(&encode_message(data))?;
+            Ok(())
+        }
+
+        fn recv(&mut self) -> NetResult<Option<Vec<Vec<u8>>>> {
+            loop {
+                if let Some(frames) = try_decode_message(&mut self.read_buf)? {
+                    return Ok(Some(frames));
+                }
+
+                let stream = self.stream.as_mut().ok_or_else(|| "not connected")?;
+                let mut chunk = [0u8; 4096];
+                match stream.read(&mut chunk) {
+                    Ok(0) => return Ok(None),
+                    Ok(n) => self.read_buf.extend_from_slice(&chunk[..n]),
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(None),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        os::unix::net::UnixStream,
+    };
+
+    /// POSIX fallback: a unix domain socket standing in for a named pipe.
+    pub struct NamedPipeIpcSocket {
+        stream: Option<UnixStream>,
+        read_buf: Vec<u8>,
+    }
+
+    impl NamedPipeIpcSocket {
+        pub fn new() -> NetResult<Self> {
+            Ok(NamedPipeIpcSocket {
+                stream: None,
+                read_buf: Vec::new(),
+            })
+        }
+    }
+
+    impl IpcSocket for NamedPipeIpcSocket {
+        fn connect(&mut self, path: &str) -> NetResult<()> {
+            let stream = UnixStream::connect(path)?;
+            stream.set_nonblocking(true)?;
+            self.stream = Some(stream);
+            Ok(())
+        }
+
+        fn close(&mut self) -> NetResult<()> {
+            self.stream = None;
+            self.read_buf.clear();
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            self.stream.is_some()
+        }
+
+        fn send(&mut self, data: &[Vec<u8>]) -> NetResult<()> {
+            let stream = self.stream.as_mut().ok_or_else(|| "not connected")?;
+            stream.write_all(&encode_message(data))?;
+            Ok(())
+        }
+
+        fn recv(&mut self) -> NetResult<Option<Vec<Vec<u8>>>> {
+            loop {
+                if let Some(frames) = try_decode_message(&mut self.read_buf)? {
+                    return Ok(Some(frames));
+                }
+
+                let stream = self.stream.as_mut().ok_or_else(|| "not connected")?;
+                let mut chunk = [0u8; 4096];
+                match stream.read(&mut chunk) {
+                    Ok(0) => return Ok(None),
+                    Ok(n) => self.read_buf.extend_from_slice(&chunk[..n]),
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(None),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+    }
+}
+
+pub use self::imp::NamedPipeIpcSocket;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_a_single_frame_message() {
+        let mut buf = encode_message(&[b"hello".to_vec()]);
+        let decoded = try_decode_message(&mut buf).unwrap().unwrap();
+        assert_eq!(decoded, vec![b"hello".to_vec()]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn it_round_trips_a_multipart_message() {
+        let mut buf = encode_message(&[vec![], vec![], b"pong".to_vec(), b"data".to_vec()]);
+        let decoded = try_decode_message(&mut buf).unwrap().unwrap();
+        assert_eq!(
+            decoded,
+            vec![vec![], vec![], b"pong".to_vec(), b"data".to_vec()]
+        );
+    }
+
+    #[test]
+    fn it_waits_for_the_rest_of_a_split_message() {
+        let whole = encode_message(&[b"hello world".to_vec()]);
+        let (first_half, second_half) = whole.split_at(whole.len() - 3);
+
+        let mut buf = first_half.to_vec();
+        assert_eq!(try_decode_message(&mut buf).unwrap(), None);
+
+        buf.extend_from_slice(second_half);
+        let decoded = try_decode_message(&mut buf).unwrap().unwrap();
+        assert_eq!(decoded, vec![b"hello world".to_vec()]);
+    }
+
+    #[test]
+    fn it_leaves_a_trailing_partial_message_in_the_buffer() {
+        let mut first = encode_message(&[b"one".to_vec()]);
+        let second = encode_message(&[b"two".to_vec()]);
+        first.extend_from_slice(&second[..second.len() - 2]);
+
+        let decoded = try_decode_message(&mut first).unwrap().unwrap();
+        assert_eq!(decoded, vec![b"one".to_vec()]);
+        assert_eq!(first.len(), second.len() - 2);
+    }
+
+    #[test]
+    fn it_rejects_a_frame_count_over_the_cap_without_allocating_it() {
+        let mut buf = ((MAX_FRAMES_PER_MESSAGE + 1) as u32).to_le_bytes().to_vec();
+        assert!(try_decode_message(&mut buf).is_err());
+    }
+
+    #[test]
+    fn it_rejects_a_frame_length_prefix_claiming_a_multi_gigabyte_frame() {
+        let mut buf = 1u32.to_le_bytes().to_vec();
+        buf.extend_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        assert!(try_decode_message(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! a typed request/response layer over `NetConnectionRelay`: stamps
+//! outgoing requests with a correlation id, resolves them when the matching
+//! response frame arrives, and can broadcast a message to every binding the
+//! relay's peer has reported. `IpcNetWorker::call`/`IpcNetWorker::broadcast`
+//! are the public entry points that use this.
+
+extern crate snowflake;
+
+use self::snowflake::ProcessUniqueId;
+use holochain_net_connection::{
+    net_connection::{NetConnection, NetConnectionRelay},
+    protocol::Protocol,
+    protocol_wrapper::StateData,
+    NetResult,
+};
+use serde_json;
+use std::{
+    collections::HashMap,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+/// a unique id stamped on every request so its response can be matched back
+pub type RequestId = ProcessUniqueId;
+
+/// the JSON field a request id (or binding id) is stamped into on the wire;
+/// `Protocol::Json` is the only variant with room for this kind of metadata,
+/// so non-JSON protocol messages can't be routed through `Router`
+const REQUEST_ID_FIELD: &str = "requestId";
+const BINDING_FIELD: &str = "toBinding";
+
+/// stamp `field` with `value` into a `Protocol::Json` frame, erroring on any
+/// other variant since there's nowhere to put the metadata
+fn stamp_json_field(msg: Protocol, field: &str, value: &str) -> NetResult<Protocol> {
+    match msg {
+        Protocol::Json(json) => {
+            let raw: String = json.into();
+            let mut parsed: serde_json::Value =
+                serde_json::from_str(&raw).map_err(|_| "expected a JSON protocol frame")?;
+            parsed[field] = serde_json::Value::String(value.to_string());
+            Ok(Protocol::Json(parsed.to_string().into()))
+        }
+        _ => bail!("can only stamp {} onto a Protocol::Json frame", field),
+    }
+}
+
+/// pull a previously stamped JSON field back out of an inbound frame, if any
+fn read_json_field(data: &Protocol, field: &str) -> Option<String> {
+    match data {
+        Protocol::Json(json) => {
+            let raw: String = json.clone().into();
+            let parsed: serde_json::Value = serde_json::from_str(&raw).ok()?;
+            parsed[field].as_str().map(|s| s.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// the bindings a relay's peer has reported via the last `StateData` it sent
+#[derive(Clone, Default)]
+pub struct Bindings {
+    bindings: Vec<String>,
+}
+
+impl Bindings {
+    pub fn update(&mut self, state: &StateData) {
+        self.bindings = state.bindings.clone();
+    }
+}
+
+/// stamps outgoing requests with a correlation id and resolves them when the
+/// matching response frame comes back through `resolve`
+pub struct Router {
+    in_flight: HashMap<String, Sender<Protocol>>,
+    bindings: Bindings,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router {
+            in_flight: HashMap::new(),
+            bindings: Bindings::default(),
+        }
+    }
+
+    /// send `msg` through `relay`, stamped with a fresh correlation id, and
+    /// return a receiver that resolves with the matching response frame once
+    /// `resolve` sees it come back
+    pub fn request(
+        &mut self,
+        relay: &mut NetConnectionRelay,
+        msg: Protocol,
+    ) -> NetResult<Receiver<Protocol>> {
+        let request_id = RequestId::new().to_string();
+        let stamped = stamp_json_field(msg, REQUEST_ID_FIELD, &request_id)?;
+
+        let (sender, receiver) = channel();
+        self.in_flight.insert(request_id, sender);
+        relay.send(stamped)?;
+        Ok(receiver)
+    }
+
+    /// feed every frame coming out of the relay through here; if it carries a
+    /// correlation id stamped by `request`, resolves the matching in-flight
+    /// request. Always returns the frame so normal (non-request) handling
+    /// can still observe it.
+    pub fn resolve(&mut self, data: Protocol) -> Protocol {
+        if let Some(request_id) = read_json_field(&data, REQUEST_ID_FIELD) {
+            if let Some(sender) = self.in_flight.remove(&request_id) {
+                let _ = sender.send(data.clone());
+            }
+        }
+        data
+    }
+
+    /// note the bindings the peer last reported, for use by `broadcast`
+    pub fn note_state(&mut self, state: &StateData) {
+        self.bindings.update(state);
+    }
+
+    /// fan `msg` out to every binding currently known for this relay's peer.
+    /// There's only one transport connection to the peer modeled in this
+    /// crate, so each binding is addressed by stamping its id into its own
+    /// copy of the frame rather than opening a connection per binding; the
+    /// peer is responsible for dispatching each copy to the right binding.
+    pub fn broadcast(&mut self, relay: &mut NetConnectionRelay, msg: Protocol) -> NetResult<()> {
+        for binding in self.bindings.bindings.clone() {
+            let addressed = stamp_json_field(msg.clone(), BINDING_FIELD, &binding)?;
+            relay.send(addressed)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use holochain_net_connection::{
+        net_connection::{NetHandler, NetWorker},
+        protocol_wrapper::ProtocolWrapper,
+    };
+    use std::{
+        convert::TryFrom,
+        sync::{Arc, Mutex},
+    };
+
+    /// a bare-bones `NetWorker` that just records whatever it's asked to
+    /// `receive`, so tests can inspect exactly what `Router` sent through it
+    struct RecordingWorker {
+        sent: Arc<Mutex<Vec<Protocol>>>,
+    }
+
+    impl NetWorker for RecordingWorker {
+        fn stop(self: Box<Self>) -> NetResult<()> {
+            Ok(())
+        }
+
+        fn receive(&mut self, data: Protocol) -> NetResult<()> {
+            self.sent.lock().unwrap().push(data);
+            Ok(())
+        }
+
+        fn tick(&mut self) -> NetResult<bool> {
+            Ok(false)
+        }
+    }
+
+    fn test_relay(sent: Arc<Mutex<Vec<Protocol>>>) -> NetConnectionRelay {
+        NetConnectionRelay::new(
+            Box::new(|_r| Ok(())),
+            Box::new(move |_h: NetHandler| {
+                let out: Box<NetWorker> = Box::new(RecordingWorker { sent: sent.clone() });
+                Ok(out)
+            }),
+        )
+        .unwrap()
+    }
+
+    fn state_with_bindings(bindings: &[&str]) -> StateData {
+        let data = Protocol::Json(
+            json!({
+                "method": "state",
+                "state": "ready",
+                "id": "test_id",
+                "bindings": bindings
+            })
+            .into(),
+        );
+        match ProtocolWrapper::try_from(&data).unwrap() {
+            ProtocolWrapper::State(state) => state,
+            _ => panic!("expected ProtocolWrapper::State"),
+        }
+    }
+
+    #[test]
+    fn it_stamps_a_request_id_onto_outgoing_requests() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut relay = test_relay(sent.clone());
+        let mut router = Router::new();
+
+        router
+            .request(&mut relay, Protocol::Json(json!({ "method": "ping" }).into()))
+            .unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(read_json_field(&sent[0], REQUEST_ID_FIELD).is_some());
+    }
+
+    #[test]
+    fn it_resolves_a_request_when_the_matching_response_comes_back() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut relay = test_relay(sent.clone());
+        let mut router = Router::new();
+
+        let receiver = router
+            .request(&mut relay, Protocol::Json(json!({ "method": "ping" }).into()))
+            .unwrap();
+
+        let request_id = read_json_field(&sent.lock().unwrap()[0], REQUEST_ID_FIELD).unwrap();
+        let response =
+            Protocol::Json(json!({ "method": "pong", REQUEST_ID_FIELD: request_id }).into());
+
+        router.resolve(response.clone());
+
+        assert_eq!(receiver.recv().unwrap(), response);
+    }
+
+    #[test]
+    fn it_leaves_frames_with_no_known_request_id_unresolved() {
+        let mut router = Router::new();
+        let untagged = Protocol::Json(json!({ "method": "state" }).into());
+
+        assert_eq!(router.resolve(untagged.clone()), untagged);
+    }
+
+    #[test]
+    fn it_broadcasts_to_every_known_binding_with_its_id_stamped() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut relay = test_relay(sent.clone());
+        let mut router = Router::new();
+
+        router.note_state(&state_with_bindings(&["a", "b"]));
+
+        router
+            .broadcast(
+                &mut relay,
+                Protocol::Json(json!({ "method": "publish" }).into()),
+            )
+            .unwrap();
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(
+            read_json_field(&sent[0], BINDING_FIELD),
+            Some("a".to_string())
+        );
+        assert_eq!(
+            read_json_field(&sent[1], BINDING_FIELD),
+            Some("b".to_string())
+        );
+    }
+}